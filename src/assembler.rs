@@ -0,0 +1,199 @@
+//! A line-oriented text assembler for `recur` programs.
+//!
+//! One mnemonic per line (`push 10`, `plus`, `dup 1`, `jmp loop`), with
+//! `label:` definitions standing in for instruction indices. Assembly is
+//! two passes: the first records the instruction index of every label,
+//! the second emits instructions and resolves label operands into
+//! numeric `Word` offsets.
+
+use crate::vm::{Instruction, InstructionType, Word};
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub struct AssembleError {
+    pub line: usize,
+    pub kind: AssembleErrorKind,
+}
+
+#[derive(Debug)]
+pub enum AssembleErrorKind {
+    UnknownMnemonic(String),
+    MissingOperand,
+    BadOperand(String),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+}
+
+impl std::fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl std::fmt::Display for AssembleErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssembleErrorKind::UnknownMnemonic(m) => write!(f, "unknown mnemonic '{m}'"),
+            AssembleErrorKind::MissingOperand => write!(f, "missing operand"),
+            AssembleErrorKind::BadOperand(o) => write!(f, "bad operand '{o}'"),
+            AssembleErrorKind::UndefinedLabel(l) => write!(f, "undefined label '{l}'"),
+            AssembleErrorKind::DuplicateLabel(l) => write!(f, "duplicate label '{l}'"),
+        }
+    }
+}
+
+const LABEL_OPERAND_MNEMONICS: [&str; 5] = ["jmp", "jmp_if", "jmp_eq", "try", "call"];
+
+/// Assemble `source` into a program, resolving label operands on jump
+/// instructions to instruction indices.
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, AssembleError> {
+    let mut labels: HashMap<String, i64> = HashMap::new();
+    let mut inst_index: i64 = 0;
+
+    // Pass 1: record the instruction index of every label.
+    for (line_no, raw_line) in source.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(label) = line.strip_suffix(':') {
+            let label = label.trim().to_string();
+            if labels.insert(label.clone(), inst_index).is_some() {
+                return Err(AssembleError {
+                    line: line_no + 1,
+                    kind: AssembleErrorKind::DuplicateLabel(label),
+                });
+            }
+            continue;
+        }
+        inst_index += 1;
+    }
+
+    // Pass 2: emit instructions, resolving operands (including labels).
+    let mut program = Vec::new();
+    for (line_no, raw_line) in source.lines().enumerate() {
+        let line_no = line_no + 1;
+        let line = raw_line.trim();
+        if line.is_empty() || line.ends_with(':') {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let mnemonic = parts.next().unwrap();
+        let operand_token = parts.next();
+
+        let inst_type = match mnemonic {
+            "push" => InstructionType::Push,
+            "pop" => InstructionType::Pop,
+            "dup" => InstructionType::Dup,
+            "plus" => InstructionType::Plus,
+            "minus" => InstructionType::Minus,
+            "mult" => InstructionType::Mult,
+            "div" => InstructionType::Div,
+            "jmp" => InstructionType::JMP,
+            "jmp_if" => InstructionType::JMP_IF,
+            "jmp_eq" => InstructionType::JMP_EQ,
+            "halt" => InstructionType::Halt,
+            "try" => InstructionType::Try,
+            "endtry" => InstructionType::EndTry,
+            "throw" => InstructionType::Throw,
+            "call" => InstructionType::Call,
+            "ret" => InstructionType::Ret,
+            "getlocal" => InstructionType::GetLocal,
+            "setlocal" => InstructionType::SetLocal,
+            "inttofloat" => InstructionType::IntToFloat,
+            "floattoint" => InstructionType::FloatToInt,
+            other => {
+                return Err(AssembleError {
+                    line: line_no,
+                    kind: AssembleErrorKind::UnknownMnemonic(other.to_string()),
+                })
+            }
+        };
+
+        let needs_operand = matches!(mnemonic, "push" | "dup" | "getlocal" | "setlocal")
+            || LABEL_OPERAND_MNEMONICS.contains(&mnemonic);
+        let operand = if needs_operand {
+            let token = operand_token.ok_or(AssembleError {
+                line: line_no,
+                kind: AssembleErrorKind::MissingOperand,
+            })?;
+
+            if LABEL_OPERAND_MNEMONICS.contains(&mnemonic) {
+                let value = if let Ok(value) = token.parse::<i64>() {
+                    value
+                } else {
+                    *labels.get(token).ok_or_else(|| AssembleError {
+                        line: line_no,
+                        kind: AssembleErrorKind::UndefinedLabel(token.to_string()),
+                    })?
+                };
+                Word::Int(value)
+            } else if mnemonic == "push" {
+                if let Ok(value) = token.parse::<i64>() {
+                    Word::Int(value)
+                } else if let Ok(value) = token.parse::<f64>() {
+                    Word::Float(value)
+                } else {
+                    return Err(AssembleError {
+                        line: line_no,
+                        kind: AssembleErrorKind::BadOperand(token.to_string()),
+                    });
+                }
+            } else {
+                let value = token.parse::<i64>().map_err(|_| AssembleError {
+                    line: line_no,
+                    kind: AssembleErrorKind::BadOperand(token.to_string()),
+                })?;
+                Word::Int(value)
+            }
+        } else {
+            Word::Int(0)
+        };
+
+        program.push(Instruction::new(inst_type, operand));
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_forward_and_backward_labels() {
+        let source = "
+            push 0
+            loop:
+            push 1
+            jmp loop
+            jmp done
+            done:
+            halt
+        ";
+
+        let program = assemble(source).expect("valid source should assemble");
+
+        assert!(matches!(program[0].inst_type, InstructionType::Push));
+        assert_eq!(program[0].operand, Word::Int(0));
+        // `loop:` labels instruction index 1.
+        assert_eq!(program[2].operand, Word::Int(1));
+        // `done:` labels instruction index 4, referenced before it is defined.
+        assert_eq!(program[3].operand, Word::Int(4));
+        assert!(matches!(program[4].inst_type, InstructionType::Halt));
+    }
+
+    #[test]
+    fn reports_undefined_label_with_line_number() {
+        let err = assemble("jmp nowhere").unwrap_err();
+        assert_eq!(err.line, 1);
+        assert!(matches!(err.kind, AssembleErrorKind::UndefinedLabel(_)));
+    }
+
+    #[test]
+    fn reports_duplicate_label() {
+        let err = assemble("a:\nhalt\na:\nhalt").unwrap_err();
+        assert!(matches!(err.kind, AssembleErrorKind::DuplicateLabel(_)));
+    }
+}
@@ -0,0 +1,197 @@
+//! Binary serialization for `recur` programs, so they can be produced
+//! offline and shipped as artifacts instead of recompiled into the binary.
+//!
+//! Layout: a 4-byte magic number, a 1-byte format version, then one
+//! 10-byte record per instruction (1 opcode byte + 1 type-tag byte + an
+//! 8-byte little-endian operand, the bits of an `i64` or an `f64`
+//! depending on the tag).
+
+use crate::vm::{Instruction, InstructionType, Word};
+
+const MAGIC: [u8; 4] = *b"RCVM";
+const VERSION: u8 = 2;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+const INST_LEN: usize = 10;
+
+const TAG_INT: u8 = 0;
+const TAG_FLOAT: u8 = 1;
+
+#[derive(Debug)]
+pub enum BytecodeError {
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    UnknownOpcode(u8),
+    UnknownTypeTag(u8),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for BytecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BytecodeError::InvalidMagic => write!(f, "not a recur bytecode file"),
+            BytecodeError::UnsupportedVersion(v) => write!(f, "unsupported bytecode version {v}"),
+            BytecodeError::Truncated => write!(f, "truncated bytecode file"),
+            BytecodeError::UnknownOpcode(op) => write!(f, "unknown opcode byte {op}"),
+            BytecodeError::UnknownTypeTag(tag) => write!(f, "unknown word type tag {tag}"),
+            BytecodeError::Io(e) => write!(f, "io error: {e}"),
+        }
+    }
+}
+
+fn opcode_of(inst_type: InstructionType) -> u8 {
+    match inst_type {
+        InstructionType::Push => 0,
+        InstructionType::Pop => 1,
+        InstructionType::Dup => 2,
+        InstructionType::Plus => 3,
+        InstructionType::Minus => 4,
+        InstructionType::Mult => 5,
+        InstructionType::Div => 6,
+        InstructionType::JMP => 7,
+        InstructionType::JMP_IF => 8,
+        InstructionType::JMP_EQ => 9,
+        InstructionType::Halt => 10,
+        InstructionType::Try => 11,
+        InstructionType::EndTry => 12,
+        InstructionType::Throw => 13,
+        InstructionType::Call => 14,
+        InstructionType::Ret => 15,
+        InstructionType::GetLocal => 16,
+        InstructionType::SetLocal => 17,
+        InstructionType::IntToFloat => 18,
+        InstructionType::FloatToInt => 19,
+    }
+}
+
+fn inst_type_of(opcode: u8) -> Option<InstructionType> {
+    match opcode {
+        0 => Some(InstructionType::Push),
+        1 => Some(InstructionType::Pop),
+        2 => Some(InstructionType::Dup),
+        3 => Some(InstructionType::Plus),
+        4 => Some(InstructionType::Minus),
+        5 => Some(InstructionType::Mult),
+        6 => Some(InstructionType::Div),
+        7 => Some(InstructionType::JMP),
+        8 => Some(InstructionType::JMP_IF),
+        9 => Some(InstructionType::JMP_EQ),
+        10 => Some(InstructionType::Halt),
+        11 => Some(InstructionType::Try),
+        12 => Some(InstructionType::EndTry),
+        13 => Some(InstructionType::Throw),
+        14 => Some(InstructionType::Call),
+        15 => Some(InstructionType::Ret),
+        16 => Some(InstructionType::GetLocal),
+        17 => Some(InstructionType::SetLocal),
+        18 => Some(InstructionType::IntToFloat),
+        19 => Some(InstructionType::FloatToInt),
+        _ => None,
+    }
+}
+
+/// Encode a single instruction as a fixed-width
+/// `[opcode, type_tag, payload_le...]` record.
+fn encode_inst(inst: &Instruction) -> [u8; INST_LEN] {
+    let mut buf = [0u8; INST_LEN];
+    buf[0] = opcode_of(inst.inst_type);
+    match inst.operand {
+        Word::Int(v) => {
+            buf[1] = TAG_INT;
+            buf[2..10].copy_from_slice(&v.to_le_bytes());
+        }
+        Word::Float(v) => {
+            buf[1] = TAG_FLOAT;
+            buf[2..10].copy_from_slice(&v.to_le_bytes());
+        }
+    }
+    buf
+}
+
+/// Decode a single fixed-width instruction record.
+///
+/// Returns `None` if the opcode or type-tag byte does not map to a known
+/// `InstructionType`/`Word` variant.
+pub fn parse_inst(buf: [u8; INST_LEN]) -> Option<Instruction> {
+    let inst_type = inst_type_of(buf[0])?;
+    let payload: [u8; 8] = buf[2..10].try_into().unwrap();
+    let operand = match buf[1] {
+        TAG_INT => Word::Int(i64::from_le_bytes(payload)),
+        TAG_FLOAT => Word::Float(f64::from_le_bytes(payload)),
+        _ => return None,
+    };
+    Some(Instruction::new(inst_type, operand))
+}
+
+/// Serialize a program into the `recur` bytecode format.
+pub fn serialize(program: &[Instruction]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_LEN + program.len() * INST_LEN);
+    buf.extend_from_slice(&MAGIC);
+    buf.push(VERSION);
+    for inst in program {
+        buf.extend_from_slice(&encode_inst(inst));
+    }
+    buf
+}
+
+/// Parse a `recur` bytecode file, validating the header and every opcode
+/// and type-tag byte instead of panicking on malformed input.
+pub fn deserialize(bytes: &[u8]) -> Result<Vec<Instruction>, BytecodeError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(BytecodeError::Truncated);
+    }
+    if bytes[0..4] != MAGIC {
+        return Err(BytecodeError::InvalidMagic);
+    }
+    let version = bytes[4];
+    if version != VERSION {
+        return Err(BytecodeError::UnsupportedVersion(version));
+    }
+
+    let body = &bytes[HEADER_LEN..];
+    if body.len() % INST_LEN != 0 {
+        return Err(BytecodeError::Truncated);
+    }
+
+    let mut program = Vec::with_capacity(body.len() / INST_LEN);
+    for chunk in body.chunks_exact(INST_LEN) {
+        let record: [u8; INST_LEN] = chunk.try_into().unwrap();
+        if inst_type_of(record[0]).is_none() {
+            return Err(BytecodeError::UnknownOpcode(record[0]));
+        }
+        if record[1] != TAG_INT && record[1] != TAG_FLOAT {
+            return Err(BytecodeError::UnknownTypeTag(record[1]));
+        }
+        let inst = parse_inst(record).expect("opcode and type tag validated above");
+        program.push(inst);
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::InstructionType;
+
+    #[test]
+    fn round_trips_int_and_float_operands() {
+        let program = vec![
+            Instruction::new(InstructionType::Push, Word::Int(42)),
+            Instruction::new(InstructionType::Push, Word::Float(3.5)),
+            Instruction::new(InstructionType::Plus, Word::Int(0)),
+            Instruction::new(InstructionType::JMP, Word::Int(0)),
+            Instruction::new(InstructionType::Halt, Word::Int(0)),
+        ];
+
+        let bytes = serialize(&program);
+        let decoded = deserialize(&bytes).expect("well-formed bytecode should decode");
+
+        assert_eq!(decoded, program);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let bytes = vec![0u8; HEADER_LEN];
+        assert!(matches!(deserialize(&bytes), Err(BytecodeError::InvalidMagic)));
+    }
+}
@@ -0,0 +1,175 @@
+//! Interactive stepping debugger REPL around `VM::execute`/`VM::dump`.
+//!
+//! Commands: `step`/`s` runs one instruction, `continue`/`c` runs until
+//! halt, trap, or breakpoint, `break <ip>` arms a breakpoint on an
+//! instruction index, `stack` dumps the data stack, `ip` prints the
+//! instruction pointer, `load <file>` loads bytecode, `save <file>`
+//! writes the current program out as bytecode, `asm <file>` assembles a
+//! text program, `reset` reinstantiates the VM, and `quit` exits.
+
+use crate::assembler;
+use crate::vm::{Trap, VM};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `on_sigint` when Ctrl-C arrives; `run_until_stop` polls it so an
+/// infinite-loop program can be interrupted back to the prompt.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+const SIGINT: i32 = 2;
+
+unsafe extern "C" {
+    fn signal(signum: i32, handler: usize) -> usize;
+}
+
+extern "C" fn on_sigint(_signum: i32) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Install a SIGINT handler via the platform's C library so `continue`
+/// can be interrupted without pulling in a signal-handling crate.
+#[cfg(unix)]
+fn install_interrupt_handler() {
+    unsafe {
+        signal(SIGINT, on_sigint as *const () as usize);
+    }
+}
+
+#[cfg(not(unix))]
+fn install_interrupt_handler() {}
+
+pub fn run(mut vm: VM) {
+    install_interrupt_handler();
+
+    println!(
+        "recur debugger — step/s, continue/c, break <ip>, stack, ip, load <file>, save <file>, asm <file>, reset, quit"
+    );
+
+    loop {
+        print!("(recur) ");
+        if io::stdout().flush().is_err() {
+            break;
+        }
+
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => break, // EOF
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                // Ctrl-C at the idle prompt: not EOF, just reprint and wait again.
+                INTERRUPTED.store(false, Ordering::SeqCst);
+                continue;
+            }
+            Err(_) => break,
+        }
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let cmd = parts.next().unwrap_or("");
+
+        match cmd {
+            "step" | "s" => step_once(&mut vm),
+            "continue" | "c" => run_until_stop(&mut vm),
+            "break" => match parts.next().and_then(|ip| ip.parse::<usize>().ok()) {
+                Some(ip) => {
+                    vm.breakpoints.insert(ip);
+                    println!("Breakpoint set at {ip}");
+                }
+                None => println!("usage: break <ip>"),
+            },
+            "stack" => vm.dump(),
+            "ip" => println!("ip = {}", vm.instruction_pointer.as_addr()),
+            "load" => match parts.next() {
+                Some(path) => match vm.load_program_from_file(path) {
+                    Ok(()) => println!("Loaded {path}"),
+                    Err(e) => println!("Failed to load {path}: {e}"),
+                },
+                None => println!("usage: load <file>"),
+            },
+            "save" => match parts.next() {
+                Some(path) => match vm.save_program_to_file(path) {
+                    Ok(()) => println!("Saved {path}"),
+                    Err(e) => println!("Failed to save {path}: {e}"),
+                },
+                None => println!("usage: save <file>"),
+            },
+            "asm" => match parts.next() {
+                Some(path) => match std::fs::read_to_string(path) {
+                    Ok(source) => match assembler::assemble(&source) {
+                        Ok(program) => {
+                            vm.program = program;
+                            println!("Assembled {path}");
+                        }
+                        Err(e) => println!("Failed to assemble {path}: {e}"),
+                    },
+                    Err(e) => println!("Failed to read {path}: {e}"),
+                },
+                None => println!("usage: asm <file>"),
+            },
+            "reset" => {
+                let program = std::mem::take(&mut vm.program);
+                let breakpoints = std::mem::take(&mut vm.breakpoints);
+                vm = VM::new();
+                vm.program = program;
+                vm.breakpoints = breakpoints;
+                println!("VM reset");
+            }
+            "quit" | "q" => break,
+            other => println!("unknown command: {other}"),
+        }
+    }
+}
+
+fn step_once(vm: &mut VM) {
+    if vm.is_halted {
+        println!("Halted");
+        return;
+    }
+    let trap = vm.execute();
+    report_trap(&trap);
+}
+
+fn run_until_stop(vm: &mut VM) {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    loop {
+        if vm.is_halted {
+            println!("Halted");
+            return;
+        }
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            println!("Interrupted");
+            return;
+        }
+
+        let ip = vm.instruction_pointer.as_addr();
+        if vm.breakpoints.contains(&ip) {
+            println!("Breakpoint hit at {ip}");
+            return;
+        }
+
+        let trap = vm.execute();
+        if !matches!(trap, Trap::NoTrap) {
+            report_trap(&trap);
+            return;
+        }
+    }
+}
+
+fn report_trap(trap: &Trap) {
+    match trap {
+        Trap::NoTrap => {}
+        Trap::TrapStackOverflow => println!("Stack overflow"),
+        Trap::TrapStackUnderflow => println!("Stack underflow"),
+        Trap::TrapDivisionByZero => println!("Division by zero"),
+        Trap::TrapIllegalAccess => println!("Illegal access"),
+        Trap::TrapUnhandledException => println!("Unhandled exception"),
+        Trap::TrapCallStackOverflow => println!("Call stack overflow"),
+        Trap::TrapCallStackUnderflow => println!("Call stack underflow"),
+        Trap::TrapTypeMismatch => println!("Type mismatch"),
+        Trap::TrapIntegerOverflow => println!("Integer overflow"),
+    }
+}
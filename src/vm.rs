@@ -0,0 +1,683 @@
+use crate::bytecode::{self, BytecodeError};
+use std::collections::HashSet;
+
+pub const STACK_CAPACITY: usize = 1024;
+pub const HANDLER_STACK_CAPACITY: usize = 256;
+pub const CALL_STACK_CAPACITY: usize = 256;
+
+/// A tagged VM value: either a 64-bit integer or a double-precision float.
+/// Arithmetic promotes int-int to int and anything touching a float to
+/// float; addressing operands (jump targets, stack offsets) are always
+/// `Int`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Word {
+    Int(i64),
+    Float(f64),
+}
+
+impl Word {
+    /// Read this word as a signed integer, truncating a float if one
+    /// somehow ends up where an integer is expected.
+    pub fn as_i64(&self) -> i64 {
+        match self {
+            Word::Int(v) => *v,
+            Word::Float(v) => *v as i64,
+        }
+    }
+
+    /// Read this word as an address/offset (instruction index or stack
+    /// offset), which are always non-negative `Int`s in practice.
+    pub fn as_addr(&self) -> usize {
+        self.as_i64() as usize
+    }
+}
+
+impl std::fmt::Display for Word {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Word::Int(v) => write!(f, "{v} (int)"),
+            Word::Float(v) => write!(f, "{v} (float)"),
+        }
+    }
+}
+
+/// Integer overflow traps rather than wrapping or panicking; float
+/// arithmetic follows IEEE and never traps.
+fn add(a: Word, b: Word) -> Result<Word, Trap> {
+    match (a, b) {
+        (Word::Int(x), Word::Int(y)) => x
+            .checked_add(y)
+            .map(Word::Int)
+            .ok_or(Trap::TrapIntegerOverflow),
+        (Word::Int(x), Word::Float(y)) => Ok(Word::Float(x as f64 + y)),
+        (Word::Float(x), Word::Int(y)) => Ok(Word::Float(x + y as f64)),
+        (Word::Float(x), Word::Float(y)) => Ok(Word::Float(x + y)),
+    }
+}
+
+fn sub(a: Word, b: Word) -> Result<Word, Trap> {
+    match (a, b) {
+        (Word::Int(x), Word::Int(y)) => x
+            .checked_sub(y)
+            .map(Word::Int)
+            .ok_or(Trap::TrapIntegerOverflow),
+        (Word::Int(x), Word::Float(y)) => Ok(Word::Float(x as f64 - y)),
+        (Word::Float(x), Word::Int(y)) => Ok(Word::Float(x - y as f64)),
+        (Word::Float(x), Word::Float(y)) => Ok(Word::Float(x - y)),
+    }
+}
+
+fn mul(a: Word, b: Word) -> Result<Word, Trap> {
+    match (a, b) {
+        (Word::Int(x), Word::Int(y)) => x
+            .checked_mul(y)
+            .map(Word::Int)
+            .ok_or(Trap::TrapIntegerOverflow),
+        (Word::Int(x), Word::Float(y)) => Ok(Word::Float(x as f64 * y)),
+        (Word::Float(x), Word::Int(y)) => Ok(Word::Float(x * y as f64)),
+        (Word::Float(x), Word::Float(y)) => Ok(Word::Float(x * y)),
+    }
+}
+
+/// Integer division by zero traps; `i64::MIN / -1` traps as an overflow
+/// rather than panicking; float division by zero follows IEEE and yields
+/// infinity/NaN.
+fn div(a: Word, b: Word) -> Result<Word, Trap> {
+    match (a, b) {
+        (Word::Int(x), Word::Int(y)) => {
+            if y == 0 {
+                return Err(Trap::TrapDivisionByZero);
+            }
+            x.checked_div(y)
+                .map(Word::Int)
+                .ok_or(Trap::TrapIntegerOverflow)
+        }
+        (Word::Int(x), Word::Float(y)) => Ok(Word::Float(x as f64 / y)),
+        (Word::Float(x), Word::Int(y)) => Ok(Word::Float(x / y as f64)),
+        (Word::Float(x), Word::Float(y)) => Ok(Word::Float(x / y)),
+    }
+}
+
+/// A `try` block in flight: where to jump on a caught trap, and how far
+/// to unwind the data stack before resuming there.
+#[derive(Clone, Copy)]
+pub struct HandlerRecord {
+    pub handler_ip: Word,
+    pub saved_size: usize,
+}
+
+/// A pending `call`: where to resume when the callee returns, and the
+/// caller's frame base to restore alongside it.
+#[derive(Clone, Copy)]
+pub struct CallFrame {
+    pub return_ip: Word,
+    pub saved_frame_base: usize,
+}
+
+pub struct VM {
+    pub data: [Word; STACK_CAPACITY],
+    pub size: usize,
+
+    pub handlers: [HandlerRecord; HANDLER_STACK_CAPACITY],
+    pub handler_count: usize,
+
+    pub call_stack: [CallFrame; CALL_STACK_CAPACITY],
+    pub call_stack_size: usize,
+    pub frame_base: usize,
+
+    pub program: Vec<Instruction>,
+    pub instruction_pointer: Word,
+    pub is_halted: bool,
+
+    /// Instruction indices the debugger REPL should stop execution at.
+    pub breakpoints: HashSet<usize>,
+}
+
+impl VM {
+    pub fn new() -> VM {
+        VM {
+            data: [Word::Int(0); STACK_CAPACITY],
+            handlers: [HandlerRecord {
+                handler_ip: Word::Int(0),
+                saved_size: 0,
+            }; HANDLER_STACK_CAPACITY],
+            handler_count: 0,
+            call_stack: [CallFrame {
+                return_ip: Word::Int(0),
+                saved_frame_base: 0,
+            }; CALL_STACK_CAPACITY],
+            call_stack_size: 0,
+            frame_base: 0,
+            instruction_pointer: Word::Int(0),
+            program: vec![],
+            size: 0,
+            is_halted: false,
+            breakpoints: HashSet::new(),
+        }
+    }
+
+    pub fn push(&mut self, word: Word) -> Option<()> {
+        //check if stack is full
+        if self.size == STACK_CAPACITY {
+            return None;
+        }
+        self.data[self.size] = word;
+        self.size += 1;
+        Some(())
+    }
+
+    pub fn pop(&mut self) -> Option<Word> {
+        //check if stack is empty
+        if self.size == 0 {
+            return None;
+        }
+        self.size -= 1;
+        Some(self.data[self.size])
+    }
+
+    /// Replace `program` with the instructions decoded from `bytes`.
+    ///
+    /// Validates the header and every opcode byte before touching `program`,
+    /// so a malformed file leaves the VM untouched rather than panicking.
+    pub fn load_program_from_bytes(&mut self, bytes: &[u8]) -> Result<(), BytecodeError> {
+        let program = bytecode::deserialize(bytes)?;
+        self.program = program;
+        Ok(())
+    }
+
+    /// Read a file produced by `bytecode::serialize` and load it as the program.
+    pub fn load_program_from_file(&mut self, path: &str) -> Result<(), BytecodeError> {
+        let bytes = std::fs::read(path).map_err(BytecodeError::Io)?;
+        self.load_program_from_bytes(&bytes)
+    }
+
+    /// Write `program` to `path` in the `bytecode` format, so it can be
+    /// shipped as an artifact and loaded with `load_program_from_file`.
+    pub fn save_program_to_file(&self, path: &str) -> Result<(), BytecodeError> {
+        let bytes = bytecode::serialize(&self.program);
+        std::fs::write(path, bytes).map_err(BytecodeError::Io)
+    }
+
+    fn advance_ip(&mut self) {
+        self.instruction_pointer = Word::Int(self.instruction_pointer.as_addr() as i64 + 1);
+    }
+
+    /// Run one instruction, recovering into the nearest installed handler
+    /// if it raises a recoverable trap and a `try` block is active.
+    pub fn execute(&mut self) -> Trap {
+        let trap = self.dispatch();
+        if trap.is_recoverable() && self.handler_count > 0 {
+            self.recover(trap_code(&trap));
+            Trap::NoTrap
+        } else {
+            trap
+        }
+    }
+
+    /// Pop the nearest handler, unwind the data stack to where it was
+    /// when the handler was installed, push the trap/throw `code`, and
+    /// jump to the handler.
+    fn recover(&mut self, code: i64) {
+        self.handler_count -= 1;
+        let handler = self.handlers[self.handler_count];
+        self.size = handler.saved_size;
+        let _ = self.push(Word::Int(code));
+        self.instruction_pointer = handler.handler_ip;
+    }
+
+    fn dispatch(&mut self) -> Trap {
+        if self.instruction_pointer.as_addr() >= self.program.len() {
+            return Trap::TrapIllegalAccess;
+        }
+
+        let ip = self.instruction_pointer.as_addr();
+        let inst = self.program[ip];
+        match inst.inst_type {
+            InstructionType::Push => {
+                self.advance_ip();
+                if self.push(inst.operand).is_none() {
+                    return Trap::TrapStackOverflow;
+                }
+                Trap::NoTrap
+            }
+            InstructionType::Plus => {
+                self.advance_ip();
+
+                let a = self.pop();
+                let b = self.pop();
+                match (a, b) {
+                    (Some(a), Some(b)) => match add(a, b) {
+                        Ok(result) => {
+                            if self.push(result).is_none() {
+                                return Trap::TrapStackOverflow;
+                            }
+                            Trap::NoTrap
+                        }
+                        Err(trap) => trap,
+                    },
+                    (None, _) => Trap::TrapStackUnderflow,
+                    (_, None) => Trap::TrapStackUnderflow,
+                }
+            }
+            InstructionType::Pop => {
+                self.advance_ip();
+                if self.pop().is_none() {
+                    return Trap::TrapStackUnderflow;
+                }
+                Trap::NoTrap
+            }
+
+            InstructionType::Dup => {
+                self.advance_ip();
+                let offset = inst.operand.as_addr();
+
+                if offset >= self.size {
+                    return Trap::TrapIllegalAccess;
+                }
+                if self.push(self.data[self.size - 1 - offset]).is_none() {
+                    return Trap::TrapStackOverflow;
+                }
+
+                Trap::NoTrap
+            }
+
+            InstructionType::Minus => {
+                self.advance_ip();
+
+                let a = self.pop();
+                let b = self.pop();
+                match (a, b) {
+                    (Some(a), Some(b)) => match sub(a, b) {
+                        Ok(result) => {
+                            if self.push(result).is_none() {
+                                return Trap::TrapStackOverflow;
+                            }
+                            Trap::NoTrap
+                        }
+                        Err(trap) => trap,
+                    },
+                    (None, _) => Trap::TrapStackUnderflow,
+                    (_, None) => Trap::TrapStackUnderflow,
+                }
+            }
+            InstructionType::Mult => {
+                self.advance_ip();
+
+                let a = self.pop();
+                let b = self.pop();
+                match (a, b) {
+                    (Some(a), Some(b)) => match mul(a, b) {
+                        Ok(result) => {
+                            if self.push(result).is_none() {
+                                return Trap::TrapStackOverflow;
+                            }
+                            Trap::NoTrap
+                        }
+                        Err(trap) => trap,
+                    },
+                    (None, _) => Trap::TrapStackUnderflow,
+                    (_, None) => Trap::TrapStackUnderflow,
+                }
+            }
+            InstructionType::Div => {
+                self.advance_ip();
+
+                let a = self.pop();
+                let b = self.pop();
+                match (a, b) {
+                    (Some(a), Some(b)) => match div(a, b) {
+                        Ok(result) => {
+                            if self.push(result).is_none() {
+                                return Trap::TrapStackOverflow;
+                            }
+                            Trap::NoTrap
+                        }
+                        Err(trap) => trap,
+                    },
+                    (None, _) => Trap::TrapStackUnderflow,
+                    (_, None) => Trap::TrapStackUnderflow,
+                }
+            }
+
+            InstructionType::JMP => {
+                self.instruction_pointer = inst.operand;
+                Trap::NoTrap
+            }
+
+            InstructionType::JMP_IF => {
+                let condition = self.pop();
+                match condition {
+                    Some(condition) => {
+                        if condition.as_i64() != 0 {
+                            self.instruction_pointer = inst.operand;
+                        } else {
+                            self.advance_ip();
+                        }
+                        Trap::NoTrap
+                    }
+                    None => Trap::TrapStackUnderflow,
+                }
+            }
+
+            InstructionType::JMP_EQ => {
+                if self.size < 2 {
+                    return Trap::TrapStackUnderflow;
+                }
+                let a = self.data[self.size - 1];
+                let b = self.data[self.size - 2];
+
+                if a == b {
+                    self.instruction_pointer = inst.operand;
+                } else {
+                    self.advance_ip();
+                }
+                self.pop();
+                Trap::NoTrap
+            }
+
+            InstructionType::Try => {
+                self.advance_ip();
+                if self.handler_count == HANDLER_STACK_CAPACITY {
+                    return Trap::TrapStackOverflow;
+                }
+                self.handlers[self.handler_count] = HandlerRecord {
+                    handler_ip: inst.operand,
+                    saved_size: self.size,
+                };
+                self.handler_count += 1;
+                Trap::NoTrap
+            }
+
+            InstructionType::EndTry => {
+                self.advance_ip();
+                if self.handler_count == 0 {
+                    return Trap::TrapStackUnderflow;
+                }
+                self.handler_count -= 1;
+                Trap::NoTrap
+            }
+
+            InstructionType::Throw => {
+                let code = self.pop();
+                match code {
+                    Some(code) => {
+                        if self.handler_count == 0 {
+                            return Trap::TrapUnhandledException;
+                        }
+                        self.recover(code.as_i64());
+                        Trap::NoTrap
+                    }
+                    None => Trap::TrapStackUnderflow,
+                }
+            }
+
+            InstructionType::Call => {
+                if self.call_stack_size == CALL_STACK_CAPACITY {
+                    return Trap::TrapCallStackOverflow;
+                }
+                self.call_stack[self.call_stack_size] = CallFrame {
+                    return_ip: Word::Int(self.instruction_pointer.as_addr() as i64 + 1),
+                    saved_frame_base: self.frame_base,
+                };
+                self.call_stack_size += 1;
+                self.frame_base = self.size;
+                self.instruction_pointer = inst.operand;
+                Trap::NoTrap
+            }
+
+            InstructionType::Ret => {
+                if self.call_stack_size == 0 {
+                    return Trap::TrapCallStackUnderflow;
+                }
+                self.call_stack_size -= 1;
+                let frame = self.call_stack[self.call_stack_size];
+                self.frame_base = frame.saved_frame_base;
+                self.instruction_pointer = frame.return_ip;
+                Trap::NoTrap
+            }
+
+            // `GetLocal`/`SetLocal` address relative to `frame_base` with a
+            // signed offset: non-negative offsets reach callee locals
+            // pushed after the call, negative offsets reach the caller's
+            // arguments pushed before it (-1 is the last argument pushed).
+            InstructionType::GetLocal => {
+                self.advance_ip();
+                let index = self.frame_base as i64 + inst.operand.as_i64();
+                if index < 0 || index as usize >= self.size {
+                    return Trap::TrapIllegalAccess;
+                }
+                if self.push(self.data[index as usize]).is_none() {
+                    return Trap::TrapStackOverflow;
+                }
+                Trap::NoTrap
+            }
+
+            InstructionType::SetLocal => {
+                self.advance_ip();
+                let index = self.frame_base as i64 + inst.operand.as_i64();
+                if index < 0 || index as usize >= self.size {
+                    return Trap::TrapIllegalAccess;
+                }
+                match self.pop() {
+                    Some(value) => {
+                        self.data[index as usize] = value;
+                        Trap::NoTrap
+                    }
+                    None => Trap::TrapStackUnderflow,
+                }
+            }
+
+            InstructionType::IntToFloat => {
+                self.advance_ip();
+                match self.pop() {
+                    Some(Word::Int(v)) => {
+                        if self.push(Word::Float(v as f64)).is_none() {
+                            return Trap::TrapStackOverflow;
+                        }
+                        Trap::NoTrap
+                    }
+                    Some(_) => Trap::TrapTypeMismatch,
+                    None => Trap::TrapStackUnderflow,
+                }
+            }
+
+            InstructionType::FloatToInt => {
+                self.advance_ip();
+                match self.pop() {
+                    Some(Word::Float(v)) => {
+                        if self.push(Word::Int(v as i64)).is_none() {
+                            return Trap::TrapStackOverflow;
+                        }
+                        Trap::NoTrap
+                    }
+                    Some(_) => Trap::TrapTypeMismatch,
+                    None => Trap::TrapStackUnderflow,
+                }
+            }
+
+            InstructionType::Halt => {
+                self.is_halted = true;
+                Trap::NoTrap
+            }
+        }
+    }
+
+    pub fn dump(&self) {
+        println!("Stack dump");
+        match self.size {
+            0 => println!("Empty"),
+            _ => {
+                for i in 0..self.size {
+                    println!("{}: {}", i, self.data[i]);
+                }
+            }
+        }
+        println!();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InstructionType {
+    Push,
+    Pop,
+    Dup,
+    Plus,
+    Minus,
+    Mult,
+    Div,
+    JMP,
+    JMP_IF,
+    JMP_EQ,
+    Halt,
+    Try,
+    EndTry,
+    Throw,
+    Call,
+    Ret,
+    GetLocal,
+    SetLocal,
+    IntToFloat,
+    FloatToInt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Instruction {
+    pub inst_type: InstructionType,
+    pub operand: Word,
+}
+
+impl Instruction {
+    pub fn new(inst_type: InstructionType, operand: Word) -> Instruction {
+        Instruction { inst_type, operand }
+    }
+}
+
+pub enum Trap {
+    TrapStackOverflow,
+    TrapStackUnderflow,
+    NoTrap,
+    TrapDivisionByZero,
+    TrapIllegalAccess,
+    TrapUnhandledException,
+    TrapCallStackOverflow,
+    TrapCallStackUnderflow,
+    TrapTypeMismatch,
+    TrapIntegerOverflow,
+}
+
+impl Trap {
+    /// Whether an installed `try` handler can catch this trap. Unhandled
+    /// throws and overflow conditions are always terminal.
+    fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            Trap::TrapStackUnderflow
+                | Trap::TrapDivisionByZero
+                | Trap::TrapIllegalAccess
+                | Trap::TrapTypeMismatch
+        )
+    }
+}
+
+/// Encode which trap fired as the `Word` pushed for a handler to inspect.
+fn trap_code(trap: &Trap) -> i64 {
+    match trap {
+        Trap::TrapStackOverflow => 0,
+        Trap::TrapStackUnderflow => 1,
+        Trap::NoTrap => 2,
+        Trap::TrapDivisionByZero => 3,
+        Trap::TrapIllegalAccess => 4,
+        Trap::TrapUnhandledException => 5,
+        Trap::TrapCallStackOverflow => 6,
+        Trap::TrapCallStackUnderflow => 7,
+        Trap::TrapTypeMismatch => 8,
+        Trap::TrapIntegerOverflow => 9,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_recovers_a_division_by_zero_into_the_handler() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            Instruction::new(InstructionType::Try, Word::Int(4)),
+            Instruction::new(InstructionType::Push, Word::Int(0)),
+            Instruction::new(InstructionType::Push, Word::Int(10)),
+            Instruction::new(InstructionType::Div, Word::Int(0)),
+            Instruction::new(InstructionType::Halt, Word::Int(0)), // handler
+        ];
+
+        while !vm.is_halted {
+            let trap = vm.execute();
+            assert!(matches!(trap, Trap::NoTrap));
+        }
+
+        // The handler saw the data stack unwound to its pre-try size (0)
+        // with the trap code for TrapDivisionByZero pushed on top.
+        assert_eq!(vm.size, 1);
+        assert_eq!(vm.data[0], Word::Int(trap_code(&Trap::TrapDivisionByZero)));
+    }
+
+    #[test]
+    fn throw_with_no_handler_is_terminal() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            Instruction::new(InstructionType::Push, Word::Int(7)),
+            Instruction::new(InstructionType::Throw, Word::Int(0)),
+        ];
+
+        assert!(matches!(vm.execute(), Trap::NoTrap)); // push
+        assert!(matches!(vm.execute(), Trap::TrapUnhandledException)); // throw
+    }
+
+    #[test]
+    fn integer_add_traps_on_overflow_instead_of_panicking() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            Instruction::new(InstructionType::Push, Word::Int(i64::MAX)),
+            Instruction::new(InstructionType::Push, Word::Int(1)),
+            Instruction::new(InstructionType::Plus, Word::Int(0)),
+        ];
+
+        assert!(matches!(vm.execute(), Trap::NoTrap)); // push MAX
+        assert!(matches!(vm.execute(), Trap::NoTrap)); // push 1
+        assert!(matches!(vm.execute(), Trap::TrapIntegerOverflow));
+    }
+
+    #[test]
+    fn integer_div_traps_on_min_by_minus_one_instead_of_panicking() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            Instruction::new(InstructionType::Push, Word::Int(-1)),
+            Instruction::new(InstructionType::Push, Word::Int(i64::MIN)),
+            Instruction::new(InstructionType::Div, Word::Int(0)),
+        ];
+
+        assert!(matches!(vm.execute(), Trap::NoTrap)); // push -1
+        assert!(matches!(vm.execute(), Trap::NoTrap)); // push MIN
+        assert!(matches!(vm.execute(), Trap::TrapIntegerOverflow));
+    }
+
+    #[test]
+    fn callee_reads_a_caller_pushed_argument_via_get_local() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            Instruction::new(InstructionType::Push, Word::Int(42)), // idx0: caller's argument
+            Instruction::new(InstructionType::Call, Word::Int(3)),  // idx1: call the callee
+            Instruction::new(InstructionType::Halt, Word::Int(0)),  // idx2: resumed on return
+            Instruction::new(InstructionType::GetLocal, Word::Int(-1)), // idx3: read the argument
+            Instruction::new(InstructionType::Ret, Word::Int(0)),   // idx4
+        ];
+
+        assert!(matches!(vm.execute(), Trap::NoTrap)); // push 42
+        assert!(matches!(vm.execute(), Trap::NoTrap)); // call
+        assert!(matches!(vm.execute(), Trap::NoTrap)); // getlocal -1
+        assert_eq!(vm.data[vm.size - 1], Word::Int(42));
+        assert!(matches!(vm.execute(), Trap::NoTrap)); // ret
+        assert!(matches!(vm.execute(), Trap::NoTrap)); // halt
+        assert!(vm.is_halted);
+    }
+}